@@ -1,14 +1,36 @@
 #![allow(dead_code)]
 use std::fmt::Display;
 
-use crate::value::ValueArray;
+use crate::value::{Value, ValueArray};
 #[derive(Debug)]
 pub enum OpCode {
     OpConstant(usize),
+    OpNil,
+    OpTrue,
+    OpFalse,
+    OpPop,
+    OpJump(usize),
+    OpJumpIfFalse(usize),
+    OpJumpIfNotNil(usize),
+    OpJumpIfEqual(usize),
+    OpBuildList(usize),
+    OpBuildMap(usize),
+    OpToString,
+    OpRange(bool),
+    OpGetIndex,
+    OpSetIndex,
+    OpInvoke(usize, usize),
     OpAdd,
     OpSubtract,
     OpMultiply,
     OpDivide,
+    OpModulo,
+    OpBitAnd,
+    OpBitOr,
+    OpBitXor,
+    OpBitNot,
+    OpShiftLeft,
+    OpShiftRight,
     OpNegate,
     OpReturn,
 }
@@ -23,11 +45,33 @@ impl Display for OpCode {
         match self {
             OpCode::OpReturn => write!(f, "OpReturn"),
             OpCode::OpConstant(i) => write!(f, "OpConstant {}", i),
+            OpCode::OpNil => write!(f, "OpNil"),
+            OpCode::OpTrue => write!(f, "OpTrue"),
+            OpCode::OpFalse => write!(f, "OpFalse"),
+            OpCode::OpPop => write!(f, "OpPop"),
+            OpCode::OpJump(i) => write!(f, "OpJump {}", i),
+            OpCode::OpJumpIfFalse(i) => write!(f, "OpJumpIfFalse {}", i),
+            OpCode::OpJumpIfNotNil(i) => write!(f, "OpJumpIfNotNil {}", i),
+            OpCode::OpJumpIfEqual(i) => write!(f, "OpJumpIfEqual {}", i),
+            OpCode::OpBuildList(n) => write!(f, "OpBuildList {}", n),
+            OpCode::OpBuildMap(n) => write!(f, "OpBuildMap {}", n),
+            OpCode::OpToString => write!(f, "OpToString"),
+            OpCode::OpRange(inclusive) => write!(f, "OpRange {}", inclusive),
+            OpCode::OpGetIndex => write!(f, "OpGetIndex"),
+            OpCode::OpSetIndex => write!(f, "OpSetIndex"),
+            OpCode::OpInvoke(name, args) => write!(f, "OpInvoke {} {}", name, args),
             OpCode::OpNegate => write!(f, "OpNegate"),
             OpCode::OpAdd => write!(f, "OpAdd"),
             OpCode::OpSubtract => write!(f, "OpSubtract"),
             OpCode::OpMultiply => write!(f, "OpMultiply"),
             OpCode::OpDivide => write!(f, "OpDivide"),
+            OpCode::OpModulo => write!(f, "OpModulo"),
+            OpCode::OpBitAnd => write!(f, "OpBitAnd"),
+            OpCode::OpBitOr => write!(f, "OpBitOr"),
+            OpCode::OpBitXor => write!(f, "OpBitXor"),
+            OpCode::OpBitNot => write!(f, "OpBitNot"),
+            OpCode::OpShiftLeft => write!(f, "OpShiftLeft"),
+            OpCode::OpShiftRight => write!(f, "OpShiftRight"),
         }
     }
 }
@@ -48,10 +92,15 @@ impl Chunk {
         self.code.push(byte);
         self.linenum.push(line);
     }
-    pub fn add_constant(&mut self, value: f64) -> usize {
+    pub fn truncate(&mut self, code_len: usize, constants_len: usize) {
+        self.code.truncate(code_len);
+        self.linenum.truncate(code_len);
+        self.constants.values.truncate(constants_len);
+    }
+    pub fn add_constant(&mut self, value: Value) -> usize {
         self.constants.write(value)
     }
-    pub fn write_constant(&mut self, value: f64, line: usize) {
+    pub fn write_constant(&mut self, value: Value, line: usize) {
         let index = self.add_constant(value);
         self.write_chunk(OpCode::OpConstant(index), line);
     }
@@ -67,8 +116,8 @@ impl Chunk {
         }
         result
     }
-    pub fn read_constant(&self, index: usize) -> f64 {
-        self.constants.values[index]
+    pub fn read_constant(&self, index: usize) -> Value {
+        self.constants.values[index].clone()
     }
     fn disassemble_instruction(&self, byte: &OpCode, offset: usize) -> String {
         match byte {
@@ -78,17 +127,52 @@ impl Chunk {
             OpCode::OpSubtract => self.simple_instruction("OP_SUBTRACT", offset),
             OpCode::OpMultiply => self.simple_instruction("OP_MULTIPLY", offset),
             OpCode::OpDivide => self.simple_instruction("OP_DIVIDE", offset),
+            OpCode::OpModulo => self.simple_instruction("OP_MODULO", offset),
+            OpCode::OpBitAnd => self.simple_instruction("OP_BIT_AND", offset),
+            OpCode::OpBitOr => self.simple_instruction("OP_BIT_OR", offset),
+            OpCode::OpBitXor => self.simple_instruction("OP_BIT_XOR", offset),
+            OpCode::OpBitNot => self.simple_instruction("OP_BIT_NOT", offset),
+            OpCode::OpShiftLeft => self.simple_instruction("OP_SHIFT_LEFT", offset),
+            OpCode::OpShiftRight => self.simple_instruction("OP_SHIFT_RIGHT", offset),
             OpCode::OpConstant(i) => {
-                let value = self.constants.values[*i];
+                let value = self.constants.values[*i].clone();
                 self.constant_instruction("OP_CONSTANT", value, offset)
             }
+            OpCode::OpNil => self.simple_instruction("OP_NIL", offset),
+            OpCode::OpTrue => self.simple_instruction("OP_TRUE", offset),
+            OpCode::OpFalse => self.simple_instruction("OP_FALSE", offset),
+            OpCode::OpPop => self.simple_instruction("OP_POP", offset),
+            OpCode::OpJump(jump) => self.jump_instruction("OP_JUMP", *jump, offset),
+            OpCode::OpJumpIfFalse(jump) => self.jump_instruction("OP_JUMP_IF_FALSE", *jump, offset),
+            OpCode::OpJumpIfNotNil(jump) => {
+                self.jump_instruction("OP_JUMP_IF_NOT_NIL", *jump, offset)
+            }
+            OpCode::OpJumpIfEqual(jump) => self.jump_instruction("OP_JUMP_IF_EQUAL", *jump, offset),
+            OpCode::OpBuildList(count) => {
+                format!("{} {} ({} items)\n", "OP_BUILD_LIST", offset, count)
+            }
+            OpCode::OpBuildMap(count) => {
+                format!("{} {} ({} entries)\n", "OP_BUILD_MAP", offset, count)
+            }
+            OpCode::OpToString => self.simple_instruction("OP_TO_STRING", offset),
+            OpCode::OpRange(false) => self.simple_instruction("OP_RANGE", offset),
+            OpCode::OpRange(true) => self.simple_instruction("OP_RANGE_INCLUSIVE", offset),
+            OpCode::OpGetIndex => self.simple_instruction("OP_GET_INDEX", offset),
+            OpCode::OpSetIndex => self.simple_instruction("OP_SET_INDEX", offset),
+            OpCode::OpInvoke(name, args) => {
+                let name = &self.constants.values[*name];
+                format!("{} {} ({} args) '{}'\n", "OP_INVOKE", offset, args, name)
+            }
         }
     }
     fn simple_instruction(&self, name: &str, offset: usize) -> String {
         format!("{} {}\n", name, offset)
     }
-    fn constant_instruction(&self, name: &str, value: f64, offset: usize) -> String {
-        format!("{} {} '{}'\n", name, offset, value)
+    fn jump_instruction(&self, name: &str, jump: usize, offset: usize) -> String {
+        format!("{} {} -> {}\n", name, offset, offset + 1 + jump)
+    }
+    fn constant_instruction(&self, name: &str, value: Value, offset: usize) -> String {
+        format!("{} {} '{}' ({})\n", name, offset, value, value.type_name())
     }
 }
 impl Default for Chunk {
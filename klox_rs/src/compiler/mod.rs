@@ -18,7 +18,7 @@ struct Parser {
     current: Token,
     previous: Token,
     had_error: bool,
-    panicMode: bool,
+    panic_mode: bool,
 }
 impl Parser {
     pub fn new() -> Parser {
@@ -26,51 +26,104 @@ impl Parser {
             current: Token::default(),
             previous: Token::default(),
             had_error: false,
-            panicMode: false,
+            panic_mode: false,
         }
     }
 }
 impl Compiler<'_> {
-    pub fn new(source: String, chunk: &'_ mut Chunk) -> Compiler {
+    pub fn new(source: String, chunk: &mut Chunk) -> Compiler<'_> {
         Compiler {
             chunk,
             scanner: Scanner::new(source),
             parser: Parser::new(),
         }
     }
+    /// On failure nothing emitted by this call is left behind in the chunk.
     pub fn compile(&mut self) -> Result<(), InterpreterError> {
-        self.advance().unwrap();
+        let code_len = self.chunk.code.len();
+        let constants_len = self.chunk.constants.values.len();
+        let result = self.compile_script();
+        if result.is_err() {
+            self.chunk.truncate(code_len, constants_len);
+        }
+        result
+    }
+    fn compile_script(&mut self) -> Result<(), InterpreterError> {
+        self.advance()?;
         self.expression()?;
-        self.consume(TokenType::Eof, "Expect end of expression.");
+        self.consume(TokenType::Eof, "Expect end of expression.")?;
         self.end_compiler();
+        if self.parser.had_error {
+            return Err(InterpreterError::CompileError(self.parser.previous.line()));
+        }
         Ok(())
     }
     fn expression(&mut self) -> Result<(), InterpreterError> {
-        self.parser_precedence(Precendence::Assignment);
-        Ok(())
+        self.parser_precedence(Precendence::Assignment)
     }
     fn advance(&mut self) -> TokenError {
         self.parser.previous = self.parser.current.clone();
         loop {
             self.parser.current = self.scanner.scan_token();
-            if self.parser.current.token_type().clone() != TokenType::Error("L".into()) {
+            if let TokenType::Error(message) = self.parser.current.token_type().clone() {
+                self.error_at_current(&message);
+            } else {
                 break Ok(self.parser.current.clone());
             }
         }
     }
-    fn consume(&mut self, token_type: TokenType, message: &str) {
+    fn check(&self, token_type: TokenType) -> bool {
+        self.parser.current.token_type().clone() == token_type
+    }
+    fn match_token(&mut self, token_type: TokenType) -> Result<bool, InterpreterError> {
+        if !self.check(token_type) {
+            return Ok(false);
+        }
+        self.advance()?;
+        Ok(true)
+    }
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<(), InterpreterError> {
         if self.parser.current.token_type().clone() == token_type {
-            self.advance();
-            return;
+            self.advance()?;
+            return Ok(());
         }
         self.error_at_current(message);
+        Ok(())
     }
     fn emit_byte(&mut self, byte: OpCode) {
         self.chunk.write_chunk(byte, self.parser.previous.line());
     }
-    fn emit_bytes(&mut self, byte1: OpCode, byte2: OpCode) {
-        self.emit_byte(byte1);
-        self.emit_byte(byte2);
+    fn identifier_constant(&mut self, name: String) -> usize {
+        self.chunk.add_constant(Value::string(name))
+    }
+    fn argument_list(&mut self) -> Result<usize, InterpreterError> {
+        let mut arg_count = 0;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.expression()?;
+                arg_count += 1;
+                if !self.match_token(TokenType::Comma)? {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        Ok(arg_count)
+    }
+    fn emit_jump(&mut self, instruction: OpCode) -> usize {
+        self.emit_byte(instruction);
+        self.chunk.code.len() - 1
+    }
+    fn patch_jump(&mut self, offset: usize) {
+        // The VM has already moved past the jump when it applies the offset.
+        let jump = self.chunk.code.len() - offset - 1;
+        self.chunk.code[offset] = match self.chunk.code[offset] {
+            OpCode::OpJump(_) => OpCode::OpJump(jump),
+            OpCode::OpJumpIfFalse(_) => OpCode::OpJumpIfFalse(jump),
+            OpCode::OpJumpIfNotNil(_) => OpCode::OpJumpIfNotNil(jump),
+            OpCode::OpJumpIfEqual(_) => OpCode::OpJumpIfEqual(jump),
+            instruction => instruction,
+        };
     }
     fn emit_return(&mut self) {
         self.emit_byte(OpCode::OpReturn);
@@ -82,29 +135,35 @@ impl Compiler<'_> {
     fn end_compiler(&mut self) {
         self.emit_return();
     }
-    fn parser_precedence(&mut self, token_type: Precendence) {
-        self.advance();
+    fn parser_precedence(&mut self, token_type: Precendence) -> Result<(), InterpreterError> {
+        self.advance()?;
         let prefix_rule = parse_rule::get_rule(self.parser.previous.token_type().clone()).prefix;
         if let Some(prefix_rule) = prefix_rule {
-            prefix_rule(self);
+            let can_assign = token_type <= Precendence::Assignment;
+            prefix_rule(self, can_assign)?;
             while token_type
                 <= parse_rule::get_rule(self.parser.current.token_type().clone()).precedence
             {
-                self.advance();
+                self.advance()?;
                 let infix_rule = parse_rule::get_rule(self.parser.previous.token_type().clone())
                     .infix
                     .unwrap();
-                infix_rule(self);
+                infix_rule(self, can_assign)?;
+            }
+            if can_assign && self.match_token(TokenType::Equal)? {
+                self.error("Invalid assignment target.");
             }
         } else {
             self.error("Expect expression")
         }
+        Ok(())
     }
     fn error_at(&mut self, token: Token, message: &str) {
-        if self.parser.panicMode {
+        if self.parser.panic_mode {
             return;
         };
-        self.parser.panicMode = true;
+        self.parser.panic_mode = true;
+        self.parser.had_error = true;
         print!("Error at line {} : {}", token.line(), message);
         if token.token_type() == &TokenType::Eof {
             print!(" at end");
@@ -113,6 +172,7 @@ impl Compiler<'_> {
         } else {
             print!(" at '{:?}'", token.token_type());
         }
+        println!();
     }
     fn error(&mut self, message: &str) {
         self.error_at(self.parser.previous.clone(), message);
@@ -2,46 +2,272 @@ use super::{
     tokenizer::{Precendence, TokenType},
     Compiler,
 };
-use crate::{chunk::OpCode, vm::InterpreterError};
-pub type ParseFn = fn(&mut Compiler) -> Result<(), InterpreterError>;
+use crate::{chunk::OpCode, value::Value, vm::InterpreterError};
+pub type ParseFn = fn(&mut Compiler, bool) -> Result<(), InterpreterError>;
 pub struct ParseRule {
     pub prefix: Option<ParseFn>,
     pub infix: Option<ParseFn>,
     pub precedence: Precendence,
 }
 
-pub fn uranary(compiler: &mut Compiler) -> Result<(), InterpreterError> {
+pub fn uranary(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
     let operator_type = compiler.parser.previous.token_type().clone();
-    compiler.parser_precedence(Precendence::Unary);
+    // A literal takes its sign directly, which is the only way to write i64::MIN.
+    if operator_type == TokenType::Minus && compiler.check(TokenType::Integer(0)) {
+        compiler.advance()?;
+        return integer(compiler, true);
+    }
+    compiler.parser_precedence(Precendence::Unary)?;
     match operator_type {
         TokenType::Minus => compiler.emit_byte(OpCode::OpNegate),
+        TokenType::Tilde => compiler.emit_byte(OpCode::OpBitNot),
         _ => Err(InterpreterError::SourceError)?,
     };
     Ok(())
 }
-fn grouping(compiler: &mut Compiler) -> Result<(), InterpreterError> {
+fn grouping(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
     compiler.expression()?;
-    compiler.consume(TokenType::RightParen, "Expect ')' after expression.");
+    compiler.consume(TokenType::RightParen, "Expect ')' after expression.")?;
     Ok(())
 }
-fn binary(compiler: &mut Compiler) -> Result<(), InterpreterError> {
+fn binary(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
     let operator_type = compiler.parser.previous.token_type().clone();
     let rule = get_rule(operator_type.clone());
-    compiler.parser_precedence(rule.precedence.increment());
+    compiler.parser_precedence(rule.precedence.increment())?;
     match operator_type {
         TokenType::Plus => compiler.emit_byte(OpCode::OpAdd),
         TokenType::Minus => compiler.emit_byte(OpCode::OpSubtract),
         TokenType::Star => compiler.emit_byte(OpCode::OpMultiply),
         TokenType::Slash => compiler.emit_byte(OpCode::OpDivide),
+        TokenType::Percent => compiler.emit_byte(OpCode::OpModulo),
+        TokenType::Ampersand => compiler.emit_byte(OpCode::OpBitAnd),
+        TokenType::Pipe => compiler.emit_byte(OpCode::OpBitOr),
+        TokenType::Caret => compiler.emit_byte(OpCode::OpBitXor),
+        TokenType::LessLess => compiler.emit_byte(OpCode::OpShiftLeft),
+        TokenType::GreaterGreater => compiler.emit_byte(OpCode::OpShiftRight),
+        _ => Err(InterpreterError::SourceError)?,
+    };
+    Ok(())
+}
+fn range(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let inclusive = compiler.parser.previous.token_type() == &TokenType::DotDotEqual;
+    // Ranges don't chain, so the right bound binds tighter than `..`.
+    compiler.parser_precedence(Precendence::Range.increment())?;
+    compiler.emit_byte(OpCode::OpRange(inclusive));
+    if compiler.check(TokenType::DotDot) || compiler.check(TokenType::DotDotEqual) {
+        compiler.error_at_current("Ranges cannot be chained.");
+    }
+    Ok(())
+}
+fn literal(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    match compiler.parser.previous.token_type() {
+        TokenType::Nil => compiler.emit_byte(OpCode::OpNil),
+        TokenType::True => compiler.emit_byte(OpCode::OpTrue),
+        TokenType::False => compiler.emit_byte(OpCode::OpFalse),
         _ => Err(InterpreterError::SourceError)?,
     };
     Ok(())
 }
-fn number(compiler: &mut Compiler) -> Result<(), InterpreterError> {
-    if let TokenType::Number(value) = compiler.parser.previous.token_type() {
-        compiler.emit_constant(*value);
+fn ternary(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let then_jump = compiler.emit_jump(OpCode::OpJumpIfFalse(0));
+    compiler.emit_byte(OpCode::OpPop);
+    compiler.expression()?;
+    compiler.consume(TokenType::Colon, "Expect ':' after then branch of '?'.")?;
+    let else_jump = compiler.emit_jump(OpCode::OpJump(0));
+    compiler.patch_jump(then_jump);
+    compiler.emit_byte(OpCode::OpPop);
+    // Same precedence again so that `a ? b : c ? d : e` nests to the right.
+    compiler.parser_precedence(Precendence::Ternary)?;
+    compiler.patch_jump(else_jump);
+    Ok(())
+}
+fn coalesce(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let end_jump = compiler.emit_jump(OpCode::OpJumpIfNotNil(0));
+    compiler.emit_byte(OpCode::OpPop);
+    compiler.parser_precedence(Precendence::Coalesce)?;
+    compiler.patch_jump(end_jump);
+    Ok(())
+}
+/// `match (subject) { 1, 2 => a, 3 => b, _ => c }` compiles to a jump chain.
+/// The subject stays on the stack while the arms are tested and is popped
+/// once an arm is taken. Without a `_` arm an unmatched subject gives nil.
+fn match_expr(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    compiler.consume(TokenType::LeftParen, "Expect '(' after 'match'.")?;
+    compiler.expression()?;
+    compiler.consume(TokenType::RightParen, "Expect ')' after match subject.")?;
+    compiler.consume(TokenType::LeftBrace, "Expect '{' before match arms.")?;
+    let mut end_jumps = Vec::new();
+    let mut has_default = false;
+    while !compiler.check(TokenType::RightBrace) && !compiler.check(TokenType::Eof) {
+        if has_default {
+            compiler.error_at_current("Default arm must be the last arm.");
+            break;
+        }
+        let mut body_jumps = Vec::new();
+        if compiler.match_token(TokenType::Identifier("_".into()))? {
+            has_default = true;
+        } else {
+            loop {
+                match_pattern(compiler)?;
+                body_jumps.push(compiler.emit_jump(OpCode::OpJumpIfEqual(0)));
+                if !compiler.match_token(TokenType::Comma)? {
+                    break;
+                }
+            }
+        }
+        let next_arm = if has_default {
+            None
+        } else {
+            Some(compiler.emit_jump(OpCode::OpJump(0)))
+        };
+        for jump in body_jumps {
+            compiler.patch_jump(jump);
+        }
+        compiler.consume(TokenType::FatArrow, "Expect '=>' after match pattern.")?;
+        compiler.emit_byte(OpCode::OpPop);
+        compiler.expression()?;
+        end_jumps.push(compiler.emit_jump(OpCode::OpJump(0)));
+        if let Some(next_arm) = next_arm {
+            compiler.patch_jump(next_arm);
+        }
+        if !compiler.match_token(TokenType::Comma)? {
+            break;
+        }
+    }
+    compiler.consume(TokenType::RightBrace, "Expect '}' after match arms.")?;
+    if !has_default {
+        compiler.emit_byte(OpCode::OpPop);
+        compiler.emit_byte(OpCode::OpNil);
+    }
+    for jump in end_jumps {
+        compiler.patch_jump(jump);
+    }
+    Ok(())
+}
+fn match_pattern(compiler: &mut Compiler) -> Result<(), InterpreterError> {
+    let negate = compiler.match_token(TokenType::Minus)?;
+    compiler.advance()?;
+    // A negative pattern is folded into a single constant.
+    match compiler.parser.previous.token_type() {
+        TokenType::Integer(_) => integer(compiler, negate)?,
+        TokenType::Number(value) if negate => compiler.emit_constant(Value::Float(-*value)),
+        TokenType::Number(_) => number(compiler, false)?,
+        TokenType::Nil | TokenType::True | TokenType::False if !negate => literal(compiler, false)?,
+        TokenType::String(_) if !negate => string(compiler, false)?,
+        _ => compiler.error("Expect literal pattern."),
+    }
+    Ok(())
+}
+fn list(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let mut count = 0;
+    while !compiler.check(TokenType::RightBracket) {
+        compiler.expression()?;
+        count += 1;
+        if !compiler.match_token(TokenType::Comma)? {
+            break;
+        }
+    }
+    compiler.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+    compiler.emit_byte(OpCode::OpBuildList(count));
+    Ok(())
+}
+fn map(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let mut count = 0;
+    while !compiler.check(TokenType::RightBrace) {
+        compiler.expression()?;
+        compiler.consume(TokenType::Colon, "Expect ':' after map key.")?;
+        compiler.expression()?;
+        count += 1;
+        if !compiler.match_token(TokenType::Comma)? {
+            break;
+        }
+    }
+    compiler.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+    compiler.emit_byte(OpCode::OpBuildMap(count));
+    Ok(())
+}
+fn subscript(compiler: &mut Compiler, can_assign: bool) -> Result<(), InterpreterError> {
+    compiler.expression()?;
+    compiler.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+    if can_assign && compiler.match_token(TokenType::Equal)? {
+        compiler.expression()?;
+        compiler.emit_byte(OpCode::OpSetIndex);
     } else {
+        compiler.emit_byte(OpCode::OpGetIndex);
+    }
+    Ok(())
+}
+fn dot(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let name = match compiler.parser.current.token_type().clone() {
+        TokenType::Identifier(name) => name,
+        _ => {
+            compiler.error_at_current("Expect method name after '.'.");
+            return Ok(());
+        }
+    };
+    compiler.advance()?;
+    let name = compiler.identifier_constant(name);
+    compiler.consume(TokenType::LeftParen, "Expect '(' after method name.")?;
+    let arg_count = compiler.argument_list()?;
+    compiler.emit_byte(OpCode::OpInvoke(name, arg_count));
+    Ok(())
+}
+fn string(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    match compiler.parser.previous.token_type() {
+        TokenType::String(value) => compiler.emit_constant(Value::string(value.clone())),
+        _ => Err(InterpreterError::SourceError)?,
+    };
+    Ok(())
+}
+/// `"a${x}b"` arrives as `Interpolation("a")`, the tokens of `x`, then
+/// `InterpolationEnd("b")`. Each embedded value is converted and concatenated.
+fn interpolation(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    let TokenType::Interpolation(segment) = compiler.parser.previous.token_type().clone() else {
+        return Err(InterpreterError::SourceError);
+    };
+    compiler.emit_constant(Value::string(segment));
+    loop {
+        compiler.expression()?;
+        compiler.emit_byte(OpCode::OpToString);
+        compiler.emit_byte(OpCode::OpAdd);
+        let (segment, done) = match compiler.parser.current.token_type().clone() {
+            TokenType::Interpolation(segment) => (segment, false),
+            TokenType::InterpolationEnd(segment) => (segment, true),
+            _ => {
+                compiler.error_at_current("Expect '}' after interpolated expression.");
+                return Ok(());
+            }
+        };
+        compiler.advance()?;
+        compiler.emit_constant(Value::string(segment));
+        compiler.emit_byte(OpCode::OpAdd);
+        if done {
+            return Ok(());
+        }
+    }
+}
+fn number(compiler: &mut Compiler, _can_assign: bool) -> Result<(), InterpreterError> {
+    match compiler.parser.previous.token_type() {
+        TokenType::Integer(_) => integer(compiler, false)?,
+        TokenType::Number(value) => compiler.emit_constant(Value::Float(*value)),
+        _ => Err(InterpreterError::SourceError)?,
+    };
+    Ok(())
+}
+/// Emits the integer literal just consumed, applying a leading `-` first
+/// so that the magnitude of i64::MIN is accepted.
+fn integer(compiler: &mut Compiler, negate: bool) -> Result<(), InterpreterError> {
+    let TokenType::Integer(magnitude) = compiler.parser.previous.token_type() else {
         return Err(InterpreterError::SourceError);
+    };
+    let value = if negate {
+        -(*magnitude as i128)
+    } else {
+        *magnitude as i128
+    };
+    match i64::try_from(value) {
+        Ok(value) => compiler.emit_constant(Value::Int(value)),
+        Err(_) => compiler.error("Integer literal too large."),
     }
     Ok(())
 }
@@ -62,51 +288,124 @@ pub fn get_rule(token: TokenType) -> ParseRule {
             infix: Some(binary),
             precedence: Precendence::Term,
         },
-        TokenType::Slash | TokenType::Star => ParseRule {
+        TokenType::Slash | TokenType::Star | TokenType::Percent => ParseRule {
             prefix: None,
             infix: Some(binary),
             precedence: Precendence::Factor,
         },
+        TokenType::LessLess | TokenType::GreaterGreater => ParseRule {
+            prefix: None,
+            infix: Some(binary),
+            precedence: Precendence::Shift,
+        },
+        TokenType::Ampersand => ParseRule {
+            prefix: None,
+            infix: Some(binary),
+            precedence: Precendence::BitAnd,
+        },
+        TokenType::Caret => ParseRule {
+            prefix: None,
+            infix: Some(binary),
+            precedence: Precendence::BitXor,
+        },
+        TokenType::Pipe => ParseRule {
+            prefix: None,
+            infix: Some(binary),
+            precedence: Precendence::BitOr,
+        },
+        TokenType::Tilde => ParseRule {
+            prefix: Some(uranary),
+            infix: None,
+            precedence: Precendence::None,
+        },
 
-        TokenType::Number(_) => ParseRule {
+        TokenType::Question => ParseRule {
+            prefix: None,
+            infix: Some(ternary),
+            precedence: Precendence::Ternary,
+        },
+        TokenType::QuestionQuestion => ParseRule {
+            prefix: None,
+            infix: Some(coalesce),
+            precedence: Precendence::Coalesce,
+        },
+        TokenType::Nil | TokenType::True | TokenType::False => ParseRule {
+            prefix: Some(literal),
+            infix: None,
+            precedence: Precendence::None,
+        },
+        TokenType::LeftBracket => ParseRule {
+            prefix: Some(list),
+            infix: Some(subscript),
+            precedence: Precendence::Call,
+        },
+        TokenType::LeftBrace => ParseRule {
+            prefix: Some(map),
+            infix: None,
+            precedence: Precendence::None,
+        },
+        TokenType::String(_) => ParseRule {
+            prefix: Some(string),
+            infix: None,
+            precedence: Precendence::None,
+        },
+        TokenType::Interpolation(_) => ParseRule {
+            prefix: Some(interpolation),
+            infix: None,
+            precedence: Precendence::None,
+        },
+        TokenType::DotDot | TokenType::DotDotEqual => ParseRule {
+            prefix: None,
+            infix: Some(range),
+            precedence: Precendence::Range,
+        },
+        TokenType::Dot => ParseRule {
+            prefix: None,
+            infix: Some(dot),
+            precedence: Precendence::Call,
+        },
+        TokenType::Match => ParseRule {
+            prefix: Some(match_expr),
+            infix: None,
+            precedence: Precendence::None,
+        },
+        TokenType::Integer(_) | TokenType::Number(_) => ParseRule {
             prefix: Some(number),
             infix: None,
             precedence: Precendence::None,
         },
         TokenType::And
         | TokenType::RightParen
-        | TokenType::LeftBrace
         | TokenType::RightBrace
         | TokenType::Comma
-        | TokenType::Dot
+        | TokenType::RightBracket
         | TokenType::Class
         | TokenType::Else
-        | TokenType::False
         | TokenType::Fun
         | TokenType::For
         | TokenType::If
-        | TokenType::Nil
         | TokenType::Or
         | TokenType::Print
         | TokenType::Semicolon
         | TokenType::Return
         | TokenType::Super
         | TokenType::This
-        | TokenType::True
+        | TokenType::Colon
         | TokenType::Var
         | TokenType::While
         | TokenType::Error(_)
+        | TokenType::InterpolationEnd(_)
         | TokenType::Eof
         | TokenType::Bang
         | TokenType::BangEqual
         | TokenType::Equal
         | TokenType::EqualEqual
+        | TokenType::FatArrow
         | TokenType::Greater
         | TokenType::GreaterEqual
         | TokenType::Less
         | TokenType::LessEqual
-        | TokenType::Identifier(_)
-        | TokenType::String(_) => ParseRule {
+        | TokenType::Identifier(_) => ParseRule {
             prefix: None,
             infix: None,
             precedence: Precendence::None,
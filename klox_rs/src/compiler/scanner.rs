@@ -9,6 +9,7 @@ static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "for" => TokenType::For,
     "fun" => TokenType::Fun,
     "if" => TokenType::If,
+    "match" => TokenType::Match,
     "nil" => TokenType::Nil,
     "or" => TokenType::Or,
     "print" => TokenType::Print,
@@ -24,6 +25,8 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
+    // One entry per open `${`, counting the unclosed `{` inside it.
+    interpolation: Vec<usize>,
 }
 macro_rules! add_matched {
     ($scanner:ident, $to_match:expr, $expected:expr, $fallback:expr) => {{
@@ -43,49 +46,108 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            interpolation: Vec::new(),
         }
     }
     pub fn scan_token(&mut self) -> Token {
-        self.start = self.current;
         self.skip_whitespace();
+        self.start = self.current;
         if self.is_at_end() {
             return self.make_token(TokenType::Eof);
         };
         let c: char = self.advance();
-        return match c {
+        match c {
             '(' => self.make_token(TokenType::LeftParen),
             ')' => self.make_token(TokenType::RightParen),
-            '{' => self.make_token(TokenType::LeftBrace),
-            '}' => self.make_token(TokenType::RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolation.last_mut() {
+                    *depth += 1;
+                }
+                self.make_token(TokenType::LeftBrace)
+            }
+            '}' => match self.interpolation.last_mut() {
+                // Closes a `${`, so the rest of the string literal follows.
+                Some(0) => {
+                    self.interpolation.pop();
+                    self.string_tok(true)
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.make_token(TokenType::RightBrace)
+                }
+                None => self.make_token(TokenType::RightBrace),
+            },
+            '[' => self.make_token(TokenType::LeftBracket),
+            ']' => self.make_token(TokenType::RightBracket),
             ';' => self.make_token(TokenType::Semicolon),
             ',' => self.make_token(TokenType::Comma),
-            '.' => self.make_token(TokenType::Dot),
+            '.' => {
+                if self.match_char('.') {
+                    add_matched!(self, '=', TokenType::DotDotEqual, TokenType::DotDot)
+                } else {
+                    self.make_token(TokenType::Dot)
+                }
+            }
             '-' => self.make_token(TokenType::Minus),
             '/' => self.make_token(TokenType::Slash),
             '+' => self.make_token(TokenType::Plus),
             '*' => self.make_token(TokenType::Star),
-            '!' => {
-                if self.match_char('=') {
-                    self.make_token(TokenType::BangEqual)
+            '%' => self.make_token(TokenType::Percent),
+            '&' => self.make_token(TokenType::Ampersand),
+            '|' => self.make_token(TokenType::Pipe),
+            '^' => self.make_token(TokenType::Caret),
+            '~' => self.make_token(TokenType::Tilde),
+            ':' => self.make_token(TokenType::Colon),
+            '?' => add_matched!(self, '?', TokenType::QuestionQuestion, TokenType::Question),
+            '!' => add_matched!(self, '=', TokenType::BangEqual, TokenType::Bang),
+            '=' => {
+                if self.match_char('>') {
+                    self.make_token(TokenType::FatArrow)
                 } else {
-                    self.make_token(TokenType::Bang)
+                    add_matched!(self, '=', TokenType::EqualEqual, TokenType::Equal)
                 }
             }
-            '!' => add_matched!(self, '=', TokenType::BangEqual, TokenType::Bang),
-            '=' => add_matched!(self, '=', TokenType::EqualEqual, TokenType::Equal),
-            '<' => add_matched!(self, '=', TokenType::LessEqual, TokenType::Less),
-            '>' => add_matched!(self, '=', TokenType::GreaterEqual, TokenType::Greater),
-            '"' => self.string_tok(),
+            '<' => {
+                if self.match_char('<') {
+                    self.make_token(TokenType::LessLess)
+                } else {
+                    add_matched!(self, '=', TokenType::LessEqual, TokenType::Less)
+                }
+            }
+            '>' => {
+                if self.match_char('>') {
+                    self.make_token(TokenType::GreaterGreater)
+                } else {
+                    add_matched!(self, '=', TokenType::GreaterEqual, TokenType::Greater)
+                }
+            }
+            '"' => self.string_tok(false),
             '0'..='9' => self.number_tok(),
             'a'..='z' | 'A'..='Z' | '_' => self.identifier_tok(),
             _ => self.error_token("Unexpected character."),
-        };
+        }
     }
     fn error_token(&self, message: &str) -> Token {
         Token::new(TokenType::Error(message.to_string()), self.line).unwrap()
     }
-    fn string_tok(&mut self) -> Token {
+    /// Scans string contents up to the closing quote or the next `${`. The
+    /// part before a `${` becomes an `Interpolation` token; the part after the
+    /// matching `}` is scanned again from here with `continued` set, and ends
+    /// in an `InterpolationEnd` token rather than a plain `String`.
+    fn string_tok(&mut self, continued: bool) -> Token {
         while self.peek() != '"' && !self.is_at_end() {
+            if self.peek() == '$' && self.peek_next() == '{' {
+                let value = self
+                    .source
+                    .chars()
+                    .skip(self.start + 1)
+                    .take(self.current - self.start - 1)
+                    .collect();
+                self.advance();
+                self.advance();
+                self.interpolation.push(0);
+                return self.make_token(TokenType::Interpolation(value));
+            }
             if self.peek() == '\n' {
                 self.line += 1;
             }
@@ -99,29 +161,41 @@ impl Scanner {
             .source
             .chars()
             .skip(self.start + 1)
-            .take(self.current - 2)
+            .take(self.current - self.start - 2)
             .collect();
-        self.make_token(TokenType::String(value))
+        if continued {
+            self.make_token(TokenType::InterpolationEnd(value))
+        } else {
+            self.make_token(TokenType::String(value))
+        }
     }
     fn number_tok(&mut self) -> Token {
         while self.peek().is_ascii_digit() {
             self.advance();
         }
+        let mut is_float = false;
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            is_float = true;
             self.advance();
             while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
-        let value = self
+        let lexeme = self
             .source
             .chars()
             .skip(self.start)
             .take(self.current - self.start)
-            .collect::<String>()
-            .parse::<f64>()
-            .unwrap();
-        self.make_token(TokenType::Number(value))
+            .collect::<String>();
+        if is_float {
+            return self.make_token(TokenType::Number(lexeme.parse::<f64>().unwrap()));
+        }
+        // The sign is its own token, so integers hold a magnitude; the parser
+        // checks that it fits an i64 once the sign is known.
+        match lexeme.parse::<u64>() {
+            Ok(value) => self.make_token(TokenType::Integer(value)),
+            Err(_) => self.error_token("Integer literal too large."),
+        }
     }
     fn identifier_tok(&mut self) -> Token {
         while self.peek().is_alphanumeric() || self.peek() == '_' {
@@ -169,13 +243,9 @@ impl Scanner {
                     self.line += 1;
                     self.advance();
                 }
-                '/' => {
-                    if self.peek_next() == '/' {
-                        while self.peek() != '\n' && !self.is_at_end() {
-                            self.advance();
-                        }
-                    } else {
-                        return;
+                '/' if self.peek_next() == '/' => {
+                    while self.peek() != '\n' && !self.is_at_end() {
+                        self.advance();
                     }
                 }
                 _ => return,
@@ -188,7 +258,68 @@ impl Scanner {
     fn peek_next(&self) -> char {
         self.source.chars().nth(self.current + 1).unwrap_or('\0')
     }
-    fn make_error(&self, message: String) -> Token {
-        Token::new(TokenType::Error(message), self.line).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Vec<TokenType> {
+        let mut scanner = Scanner::new(source.to_string());
+        let mut tokens = Vec::new();
+        loop {
+            let token = scanner.scan_token();
+            if *token.token_type() == TokenType::Eof {
+                return tokens;
+            }
+            tokens.push(token.token_type().clone());
+        }
+    }
+
+    #[test]
+    fn braces_nested_in_interpolation() {
+        assert_eq!(
+            scan(r#""a${ {"k": 1}["k"] }b""#),
+            vec![
+                TokenType::Interpolation("a".to_string()),
+                TokenType::LeftBrace,
+                TokenType::String("k".to_string()),
+                TokenType::Colon,
+                TokenType::Integer(1),
+                TokenType::RightBrace,
+                TokenType::LeftBracket,
+                TokenType::String("k".to_string()),
+                TokenType::RightBracket,
+                TokenType::InterpolationEnd("b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn brace_inside_interpolated_string() {
+        assert_eq!(
+            scan(r#""${"}"}""#),
+            vec![
+                TokenType::Interpolation(String::new()),
+                TokenType::String("}".to_string()),
+                TokenType::InterpolationEnd(String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_literal_beyond_u64() {
+        assert_eq!(
+            scan("18446744073709551616"),
+            vec![TokenType::Error("Integer literal too large.".to_string())]
+        );
+    }
+
+    #[test]
+    fn brace_outside_interpolation() {
+        assert_eq!(
+            scan(r#"} "x""#),
+            vec![TokenType::RightBrace, TokenType::String("x".to_string())]
+        );
     }
 }
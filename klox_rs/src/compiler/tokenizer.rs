@@ -8,25 +8,43 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
+    DotDot,
+    DotDotEqual,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Colon,
     // One or two character tokens.
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
+    Question,
+    QuestionQuestion,
     // Literals.
     Identifier(String),
     String(String),
+    Interpolation(String),
+    InterpolationEnd(String),
+    Integer(#[derivative(PartialEq = "ignore")] u64),
     Number(
         #[derivative(PartialEq = "ignore")]
         #[derivative(Hash = "ignore")]
@@ -40,6 +58,7 @@ pub enum TokenType {
     For,
     Fun,
     If,
+    Match,
     Nil,
     Or,
     Print,
@@ -57,13 +76,20 @@ pub enum TokenType {
 pub enum Precendence {
     None,
     Assignment, // =
+    Ternary,    // ?:
+    Coalesce,   // ??
+    Range,      // .. ..=
     Or,         // or
     And,        // and
+    BitOr,      // |
+    BitXor,     // ^
+    BitAnd,     // &
     Equality,   // == !=
     Comparison, // < > <= >=
+    Shift,      // << >>
     Term,       // + -
-    Factor,     // * /
-    Unary,      // ! -
+    Factor,     // * / %
+    Unary,      // ! - ~
     Call,       // . ()
     Primary,
 }
@@ -71,11 +97,18 @@ impl Precendence {
     pub fn increment(&self) -> Precendence {
         match self {
             Precendence::None => Precendence::Assignment,
-            Precendence::Assignment => Precendence::Or,
+            Precendence::Assignment => Precendence::Ternary,
+            Precendence::Ternary => Precendence::Coalesce,
+            Precendence::Coalesce => Precendence::Range,
+            Precendence::Range => Precendence::Or,
             Precendence::Or => Precendence::And,
-            Precendence::And => Precendence::Equality,
+            Precendence::And => Precendence::BitOr,
+            Precendence::BitOr => Precendence::BitXor,
+            Precendence::BitXor => Precendence::BitAnd,
+            Precendence::BitAnd => Precendence::Equality,
             Precendence::Equality => Precendence::Comparison,
-            Precendence::Comparison => Precendence::Term,
+            Precendence::Comparison => Precendence::Shift,
+            Precendence::Shift => Precendence::Term,
             Precendence::Term => Precendence::Factor,
             Precendence::Factor => Precendence::Unary,
             Precendence::Unary => Precendence::Call,
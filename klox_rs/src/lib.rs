@@ -1,4 +1,7 @@
 pub mod chunk;
 pub mod compiler;
+pub mod loader;
+pub mod object;
+pub mod table;
 pub mod value;
 pub mod vm;
@@ -0,0 +1,146 @@
+use std::{collections::HashMap, fmt::Display, io, path::PathBuf, str::Utf8Error};
+
+/// Supplies module source to the VM, so the embedder decides where scripts
+/// come from.
+pub trait ModuleLoader {
+    fn load(&mut self, path: &str) -> Result<String, LoadError>;
+}
+
+/// A module that could not be loaded, and why.
+#[derive(Debug)]
+pub struct LoadError {
+    pub path: String,
+    pub kind: LoadErrorKind,
+}
+#[derive(Debug)]
+pub enum LoadErrorKind {
+    NotFound,
+    InvalidUtf8(Utf8Error),
+    Io(io::Error),
+}
+impl LoadError {
+    pub fn new(path: &str, kind: LoadErrorKind) -> Self {
+        Self {
+            path: path.to_string(),
+            kind,
+        }
+    }
+}
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            LoadErrorKind::NotFound => write!(f, "Module '{}' not found", self.path),
+            LoadErrorKind::InvalidUtf8(err) => {
+                write!(f, "Module '{}' is not valid UTF-8: {}", self.path, err)
+            }
+            LoadErrorKind::Io(err) => write!(f, "Could not read module '{}': {}", self.path, err),
+        }
+    }
+}
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            LoadErrorKind::NotFound => None,
+            LoadErrorKind::InvalidUtf8(err) => Some(err),
+            LoadErrorKind::Io(err) => Some(err),
+        }
+    }
+}
+
+/// Reads modules from the filesystem, relative to `root`.
+pub struct FileLoader {
+    root: PathBuf,
+}
+impl FileLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+impl Default for FileLoader {
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+impl ModuleLoader for FileLoader {
+    fn load(&mut self, path: &str) -> Result<String, LoadError> {
+        let source = std::fs::read(self.root.join(path)).map_err(|err| {
+            let kind = match err.kind() {
+                io::ErrorKind::NotFound => LoadErrorKind::NotFound,
+                _ => LoadErrorKind::Io(err),
+            };
+            LoadError::new(path, kind)
+        })?;
+        String::from_utf8(source)
+            .map_err(|err| LoadError::new(path, LoadErrorKind::InvalidUtf8(err.utf8_error())))
+    }
+}
+
+/// Serves modules from buffers handed over by the host, for example the
+/// bytes written to a character device.
+#[derive(Default)]
+pub struct MemoryLoader {
+    modules: HashMap<String, Vec<u8>>,
+}
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert(&mut self, path: impl Into<String>, source: impl Into<Vec<u8>>) {
+        self.modules.insert(path.into(), source.into());
+    }
+}
+impl ModuleLoader for MemoryLoader {
+    fn load(&mut self, path: &str) -> Result<String, LoadError> {
+        let source = self
+            .modules
+            .get(path)
+            .ok_or_else(|| LoadError::new(path, LoadErrorKind::NotFound))?;
+        std::str::from_utf8(source)
+            .map(str::to_owned)
+            .map_err(|err| LoadError::new(path, LoadErrorKind::InvalidUtf8(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_loader_hit_and_miss() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("main.lox", "1 + 2");
+        assert_eq!(loader.load("main.lox").unwrap(), "1 + 2");
+        let err = loader.load("other.lox").unwrap_err();
+        assert_eq!(err.path, "other.lox");
+        assert!(matches!(err.kind, LoadErrorKind::NotFound));
+    }
+
+    #[test]
+    fn memory_loader_invalid_utf8() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("bad.lox", vec![b'1', 0xff]);
+        let err = loader.load("bad.lox").unwrap_err();
+        assert!(matches!(err.kind, LoadErrorKind::InvalidUtf8(e) if e.valid_up_to() == 1));
+    }
+
+    #[test]
+    fn file_loader_maps_errors() {
+        let root = std::env::temp_dir().join(format!("klox-loader-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::fs::write(root.join("main.lox"), "1 + 2").unwrap();
+        std::fs::write(root.join("bad.lox"), [0xff]).unwrap();
+        let mut loader = FileLoader::new(&root);
+        let main = loader.load("main.lox");
+        let missing = loader.load("missing.lox");
+        let bad = loader.load("bad.lox");
+        let dir = loader.load("dir");
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(main.unwrap(), "1 + 2");
+        assert!(matches!(missing.unwrap_err().kind, LoadErrorKind::NotFound));
+        assert!(matches!(
+            bad.unwrap_err().kind,
+            LoadErrorKind::InvalidUtf8(_)
+        ));
+        assert!(matches!(dir.unwrap_err().kind, LoadErrorKind::Io(_)));
+    }
+}
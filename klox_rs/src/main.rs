@@ -26,7 +26,8 @@ fn repl() -> Result<(), InterpreterError> {
             Ok(line) => {
                 rl.add_history_entry(line.as_str())
                     .map_err(|_| InterpreterError::SourceError)?;
-                vm.interpret(line)?;
+                // The VM has already reported the error; keep the session going.
+                let _ = vm.interpret(line);
             }
             Err(ReadlineError::Interrupted) => {
                 break;
@@ -47,7 +48,6 @@ fn repl() -> Result<(), InterpreterError> {
 
 fn run_file(path: String) -> Result<(), InterpreterError> {
     let mut vm = VM::new();
-    let source = std::fs::read_to_string(path).map_err(|_| InterpreterError::SourceError)?;
-    vm.interpret(source)?;
+    vm.interpret_module(&path)?;
     Ok(())
 }
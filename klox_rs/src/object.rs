@@ -0,0 +1,72 @@
+use std::{cell::RefCell, fmt::Display};
+
+use crate::{table::Table, value::Value};
+
+/// Heap objects. Values hold them behind an `Rc`, so copying a value
+/// shares the object rather than duplicating it.
+#[derive(Debug, PartialEq)]
+pub enum Obj {
+    String(String),
+    List(RefCell<Vec<Value>>),
+    Map(RefCell<Table>),
+    Range(Range),
+}
+/// Integer range; `end` is excluded unless `inclusive` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+}
+impl Obj {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Obj::String(_) => "string",
+            Obj::List(_) => "list",
+            Obj::Map(_) => "map",
+            Obj::Range(_) => "range",
+        }
+    }
+}
+impl Display for Obj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Obj::String(s) => write!(f, "{}", s),
+            Obj::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_item(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Obj::Map(table) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in table.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_item(f, &key.to_value())?;
+                    write!(f, ": ")?;
+                    write_item(f, value)?;
+                }
+                write!(f, "}}")
+            }
+            Obj::Range(range) => {
+                let dots = if range.inclusive { "..=" } else { ".." };
+                write!(f, "{}{}{}", range.start, dots, range.end)
+            }
+        }
+    }
+}
+/// Strings nested in a collection are quoted so `["1"]` and `[1]` differ.
+fn write_item(f: &mut std::fmt::Formatter<'_>, item: &Value) -> std::fmt::Result {
+    match item {
+        Value::Obj(obj) if matches!(obj.as_ref(), Obj::String(_)) => {
+            write!(f, "{:?}", obj.to_string())
+        }
+        _ => write!(f, "{}", item),
+    }
+}
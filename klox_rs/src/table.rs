@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::{
+    object::Obj,
+    value::{float_to_int, Value},
+};
+
+/// The subset of values that can be used as table keys. Floats with an
+/// integral value are stored as ints so that `1` and `1.0` name the same
+/// entry, matching `Value::equals`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TableKey {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(OrderedFloat<f64>),
+    String(String),
+}
+impl TableKey {
+    pub fn new(value: &Value) -> Option<TableKey> {
+        match value {
+            Value::Nil => Some(TableKey::Nil),
+            Value::Bool(b) => Some(TableKey::Bool(*b)),
+            Value::Int(i) => Some(TableKey::Int(*i)),
+            Value::Float(f) => Some(match float_to_int(*f) {
+                Some(i) => TableKey::Int(i),
+                None => TableKey::Float(OrderedFloat(*f)),
+            }),
+            Value::Obj(obj) => match obj.as_ref() {
+                Obj::String(s) => Some(TableKey::String(s.clone())),
+                _ => None,
+            },
+        }
+    }
+    pub fn to_value(&self) -> Value {
+        match self {
+            TableKey::Nil => Value::Nil,
+            TableKey::Bool(b) => Value::Bool(*b),
+            TableKey::Int(i) => Value::Int(*i),
+            TableKey::Float(f) => Value::Float(f.0),
+            TableKey::String(s) => Value::string(s.clone()),
+        }
+    }
+}
+
+/// Hash table that iterates in insertion order. Overwriting a key keeps
+/// its original position; removing and re-adding it moves it to the end.
+#[derive(Debug, Default)]
+pub struct Table {
+    // Removed entries leave a `None` behind so that later indices stay
+    // valid; the tombstones are swept once they outnumber live entries.
+    entries: Vec<Option<(TableKey, Value)>>,
+    indices: HashMap<TableKey, usize>,
+}
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+    pub fn get(&self, key: &TableKey) -> Option<&Value> {
+        let index = *self.indices.get(key)?;
+        self.entries[index].as_ref().map(|(_, value)| value)
+    }
+    /// Returns true if the key was not already present.
+    pub fn set(&mut self, key: TableKey, value: Value) -> bool {
+        if let Some(&index) = self.indices.get(&key) {
+            if let Some((_, slot)) = &mut self.entries[index] {
+                *slot = value;
+            }
+            return false;
+        }
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push(Some((key, value)));
+        true
+    }
+    pub fn remove(&mut self, key: &TableKey) -> Option<Value> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries[index].take()?;
+        if self.entries.len() > 2 * self.indices.len() {
+            self.compact();
+        }
+        Some(value)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&TableKey, &Value)> {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+    fn compact(&mut self) {
+        self.entries.retain(Option::is_some);
+        for (index, (key, _)) in self.entries.iter().flatten().enumerate() {
+            *self.indices.get_mut(key).unwrap() = index;
+        }
+    }
+}
+// Tables are equal when they hold the same entries in the same order,
+// whatever tombstones each has left behind.
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(table: &Table) -> Vec<TableKey> {
+        table.iter().map(|(key, _)| key.clone()).collect()
+    }
+    fn key(value: Value) -> TableKey {
+        TableKey::new(&value).unwrap()
+    }
+
+    #[test]
+    fn overwrite_keeps_position() {
+        let mut table = Table::new();
+        assert!(table.set(key(Value::Int(1)), Value::Nil));
+        assert!(table.set(key(Value::Int(2)), Value::Nil));
+        assert!(!table.set(key(Value::Int(1)), Value::Bool(true)));
+        assert_eq!(keys(&table), vec![TableKey::Int(1), TableKey::Int(2)]);
+        assert_eq!(table.get(&TableKey::Int(1)), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn remove_then_reinsert_moves_to_end() {
+        let mut table = Table::new();
+        for i in 0..3 {
+            table.set(key(Value::Int(i)), Value::Int(i * 10));
+        }
+        assert_eq!(table.remove(&TableKey::Int(0)), Some(Value::Int(0)));
+        assert_eq!(table.get(&TableKey::Int(2)), Some(&Value::Int(20)));
+        table.set(key(Value::Int(0)), Value::Int(0));
+        assert_eq!(
+            keys(&table),
+            vec![TableKey::Int(1), TableKey::Int(2), TableKey::Int(0)]
+        );
+        assert_eq!(table.remove(&TableKey::Int(0)), Some(Value::Int(0)));
+        assert_eq!(table.remove(&TableKey::Int(0)), None);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn compaction_keeps_order_and_lookups() {
+        let mut table = Table::new();
+        for i in 0..8 {
+            table.set(key(Value::Int(i)), Value::Int(i));
+        }
+        for i in 0..6 {
+            table.remove(&TableKey::Int(i));
+        }
+        // The fifth removal sweeps; the sixth leaves one tombstone.
+        assert_eq!(table.entries.len(), 3);
+        table.set(key(Value::Int(0)), Value::Int(0));
+        assert_eq!(
+            keys(&table),
+            vec![TableKey::Int(6), TableKey::Int(7), TableKey::Int(0)]
+        );
+        assert_eq!(table.get(&TableKey::Int(7)), Some(&Value::Int(7)));
+        assert_eq!(table.remove(&TableKey::Int(6)), Some(Value::Int(6)));
+        assert_eq!(keys(&table), vec![TableKey::Int(7), TableKey::Int(0)]);
+    }
+
+    #[test]
+    fn integral_float_shares_int_key() {
+        let mut table = Table::new();
+        table.set(key(Value::Int(1)), Value::string("int".to_string()));
+        assert!(!table.set(key(Value::Float(1.0)), Value::string("float".to_string())));
+        assert_eq!(table.len(), 1);
+        assert_eq!(
+            table.get(&key(Value::Int(1))),
+            Some(&Value::string("float".to_string()))
+        );
+        assert_eq!(key(Value::Float(1.5)), TableKey::Float(OrderedFloat(1.5)));
+    }
+}
@@ -1,4 +1,109 @@
-pub type Value = f64;
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::{
+    object::{Obj, Range},
+    table::Table,
+};
+
+/// Integers stay exact; any operation mixing an `Int` with a `Float`
+/// promotes the integer and produces a `Float`.
+///
+/// Int arithmetic that overflows is a runtime error rather than wrapping.
+/// `/` and `%` on two ints follow C: the quotient truncates toward zero,
+/// the remainder takes the sign of the dividend, and an int divisor of
+/// zero is a runtime error. With a float operand they use IEEE semantics,
+/// so `1.0 / 0` is `inf`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Obj(Rc<Obj>),
+}
+impl Value {
+    pub fn string(s: String) -> Value {
+        Value::Obj(Rc::new(Obj::String(s)))
+    }
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::Obj(Rc::new(Obj::List(RefCell::new(items))))
+    }
+    pub fn range(range: Range) -> Value {
+        Value::Obj(Rc::new(Obj::Range(range)))
+    }
+    pub fn map(table: Table) -> Value {
+        Value::Obj(Rc::new(Obj::Map(RefCell::new(table))))
+    }
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+    /// Numbers compare by value across kinds, so `1 == 1.0`. The comparison
+    /// is exact: an int never equals a float that only rounds to it.
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => {
+                float_to_int(*b) == Some(*a)
+            }
+            (Value::Float(a), Value::Float(b)) => a == b,
+            // Strings and ranges compare by content, other objects by identity.
+            (Value::Obj(a), Value::Obj(b)) => match (a.as_ref(), b.as_ref()) {
+                (Obj::String(a), Obj::String(b)) => a == b,
+                (Obj::Range(a), Obj::Range(b)) => a == b,
+                _ => Rc::ptr_eq(a, b),
+            },
+            _ => self == other,
+        }
+    }
+    pub fn is_string(&self) -> bool {
+        matches!(self, Value::Obj(obj) if matches!(obj.as_ref(), Obj::String(_)))
+    }
+    pub fn as_obj(&self) -> Option<&Obj> {
+        match self {
+            Value::Obj(obj) => Some(obj.as_ref()),
+            _ => None,
+        }
+    }
+    pub fn as_range(&self) -> Option<&Range> {
+        match self.as_obj() {
+            Some(Obj::Range(range)) => Some(range),
+            _ => None,
+        }
+    }
+    pub fn is_falsey(&self) -> bool {
+        matches!(self, Value::Nil | Value::Bool(false))
+    }
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Obj(obj) => obj.type_name(),
+        }
+    }
+}
+/// The int an integral float stands for, if it fits in an i64.
+pub fn float_to_int(f: f64) -> Option<i64> {
+    // i64::MAX as f64 rounds up to 2^63, which is already out of range.
+    (f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64).then_some(f as i64)
+}
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            // Debug keeps the trailing ".0" so floats never print like ints.
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Obj(obj) => write!(f, "{}", obj),
+        }
+    }
+}
 pub struct ValueArray {
     pub values: Vec<Value>,
 }
@@ -1,8 +1,11 @@
-use std::fmt::Debug;
+use std::{cell::RefCell, fmt::Debug};
 
 use crate::{
     chunk::{Chunk, OpCode},
     compiler::Compiler,
+    loader::{FileLoader, LoadError, ModuleLoader},
+    object::{Obj, Range},
+    table::{Table, TableKey},
     value::Value,
 };
 #[derive(Debug)]
@@ -11,9 +14,15 @@ pub enum InterpreterError {
     RuntimeError(usize),
     ScanningError(usize),
     SourceError,
+    LoadError(LoadError),
     EndOfFile,
 }
 impl std::error::Error for InterpreterError {}
+impl From<LoadError> for InterpreterError {
+    fn from(err: LoadError) -> Self {
+        InterpreterError::LoadError(err)
+    }
+}
 pub enum InterpreterStatus {
     Running,
     Returning,
@@ -24,6 +33,7 @@ impl std::fmt::Display for InterpreterError {
             InterpreterError::CompileError(line) => write!(f, "Compile error at line {}", line),
             InterpreterError::RuntimeError(line) => write!(f, "Runtime error at line {}", line),
             InterpreterError::SourceError => write!(f, "Source error"),
+            InterpreterError::LoadError(err) => write!(f, "{}", err),
             InterpreterError::ScanningError(line) => write!(f, "Scanning error at line {}", line),
             InterpreterError::EndOfFile => write!(f, "End of file"),
         }
@@ -34,29 +44,110 @@ pub struct VM {
     ip: usize,
     stack: Vec<Value>,
     stack_top: usize,
+    loader: Box<dyn ModuleLoader>,
 }
 //Binary op macro
+//Two ints use the checked integer op, anything else is promoted to float.
 macro_rules! binary_op {
+    ($vm:ident, $checked:ident, $op:tt) => {
+        binary_op!($vm, $checked, $op, "Operands must be numbers.")
+    };
+    ($vm:ident, $checked:ident, $op:tt, $message:expr) => {{
+        let b = $vm.pop()?;
+        let a = $vm.pop()?;
+        let result = match (a, b) {
+            (Value::Int(a), Value::Int(b)) => Value::Int(
+                a.$checked(b)
+                    .ok_or_else(|| $vm.runtime_error("Integer overflow."))?,
+            ),
+            (a, b) => match (a.as_float(), b.as_float()) {
+                (Some(a), Some(b)) => Value::Float(a $op b),
+                _ => Err($vm.runtime_error($message))?,
+            },
+        };
+        $vm.push(result);
+    }};
+}
+//Like binary_op, but an integer right operand of zero is a runtime error.
+//Int / Int truncates toward zero like C; otherwise IEEE semantics apply.
+//$int_op returns None on overflow.
+macro_rules! division_op {
+    ($vm:ident, $int_op:expr, $op:tt) => {{
+        let b = $vm.pop()?;
+        let a = $vm.pop()?;
+        let result = match (a, b) {
+            (Value::Int(_), Value::Int(0)) => Err($vm.runtime_error("Division by zero."))?,
+            (Value::Int(a), Value::Int(b)) => Value::Int(
+                ($int_op)(a, b)
+                    .ok_or_else(|| $vm.runtime_error("Integer overflow."))?,
+            ),
+            (a, b) => match (a.as_float(), b.as_float()) {
+                (Some(a), Some(b)) => Value::Float(a $op b),
+                _ => Err($vm.runtime_error("Operands must be numbers."))?,
+            },
+        };
+        $vm.push(result);
+    }};
+}
+//Bitwise ops are only defined on ints.
+macro_rules! bitwise_op {
     ($vm:ident, $op:tt) => {{
         let b = $vm.pop()?;
         let a = $vm.pop()?;
-        $vm.push(a $op b);
+        match (a, b) {
+            (Value::Int(a), Value::Int(b)) => $vm.push(Value::Int(a $op b)),
+            _ => Err($vm.runtime_error("Operands must be integers."))?,
+        };
+    }};
+}
+//Shift amounts outside 0..64 are a runtime error rather than wrapping.
+macro_rules! shift_op {
+    ($vm:ident, $checked:ident) => {{
+        let b = $vm.pop()?;
+        let a = $vm.pop()?;
+        match (a, b) {
+            (Value::Int(a), Value::Int(b)) => {
+                let result = u32::try_from(b)
+                    .ok()
+                    .and_then(|b| a.$checked(b))
+                    .ok_or_else(|| $vm.runtime_error("Shift amount out of range."))?;
+                $vm.push(Value::Int(result));
+            }
+            _ => Err($vm.runtime_error("Operands must be integers."))?,
+        };
     }};
 }
 impl VM {
     pub fn new() -> VM {
+        VM::with_loader(FileLoader::default())
+    }
+    pub fn with_loader(loader: impl ModuleLoader + 'static) -> VM {
         VM {
             chunk: Chunk::new(),
             ip: 0,
             stack: Vec::new(),
             stack_top: 0,
+            loader: Box::new(loader),
         }
     }
+    /// Each call compiles into a fresh chunk, so nothing from an earlier
+    /// script, failed or not, is executed again.
     pub fn interpret(&mut self, source: String) -> Result<(), InterpreterError> {
-        let mut codegen = Compiler::new(source, &mut self.chunk);
-        codegen.compile()?;
-        self.run()?;
-        Ok(())
+        let mut chunk = Chunk::new();
+        Compiler::new(source, &mut chunk).compile()?;
+        self.chunk = chunk;
+        self.ip = 0;
+        let result = self.run();
+        if result.is_err() {
+            self.stack.clear();
+            self.stack_top = 0;
+        }
+        result
+    }
+    /// Fetches `path` through the VM's module loader and runs it.
+    pub fn interpret_module(&mut self, path: &str) -> Result<(), InterpreterError> {
+        let source = self.loader.load(path)?;
+        self.interpret(source)
     }
     fn run(&mut self) -> Result<(), InterpreterError> {
         loop {
@@ -80,43 +171,380 @@ impl VM {
                 println!("{}", self.pop()?);
                 return Ok(InterpreterStatus::Returning);
             }
-            OpCode::OpAdd => binary_op!(self, +),
-            OpCode::OpSubtract => binary_op!(self, -),
-            OpCode::OpMultiply => binary_op!(self, *),
-            OpCode::OpDivide => binary_op!(self, /),
+            OpCode::OpAdd => {
+                if matches!(self.stack.as_slice(), [.., a, b] if a.is_string() && b.is_string()) {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(Value::string(format!("{}{}", a, b)));
+                } else {
+                    binary_op!(self, checked_add, +, "Operands must be two numbers or two strings.")
+                }
+            }
+            OpCode::OpSubtract => binary_op!(self, checked_sub, -),
+            OpCode::OpMultiply => binary_op!(self, checked_mul, *),
+            OpCode::OpDivide => division_op!(self, i64::checked_div, /),
+            // i64::MIN % -1 is 0; only the matching division overflows.
+            OpCode::OpModulo => {
+                division_op!(self, |a: i64, b: i64| Some(a.wrapping_rem(b)), %)
+            }
+            OpCode::OpBitAnd => bitwise_op!(self, &),
+            OpCode::OpBitOr => bitwise_op!(self, |),
+            OpCode::OpBitXor => bitwise_op!(self, ^),
+            OpCode::OpShiftLeft => shift_op!(self, checked_shl),
+            OpCode::OpShiftRight => shift_op!(self, checked_shr),
+            OpCode::OpBitNot => match self.pop()? {
+                Value::Int(i) => {
+                    self.push(Value::Int(!i));
+                }
+                _ => Err(self.runtime_error("Operand must be an integer."))?,
+            },
             OpCode::OpNegate => {
-                let value = self.pop()?;
-                self.push(-value);
+                let value = match self.pop()? {
+                    Value::Int(i) => Value::Int(
+                        i.checked_neg()
+                            .ok_or_else(|| self.runtime_error("Integer overflow."))?,
+                    ),
+                    Value::Float(f) => Value::Float(-f),
+                    _ => Err(self.runtime_error("Operand must be a number."))?,
+                };
+                self.push(value);
             }
             OpCode::OpConstant(index) => {
                 let constant = self.read_constant(index);
                 self.push(constant);
             }
+            OpCode::OpNil => {
+                self.push(Value::Nil);
+            }
+            OpCode::OpTrue => {
+                self.push(Value::Bool(true));
+            }
+            OpCode::OpFalse => {
+                self.push(Value::Bool(false));
+            }
+            OpCode::OpPop => {
+                self.pop()?;
+            }
+            OpCode::OpJump(offset) => self.ip += offset,
+            OpCode::OpJumpIfFalse(offset) => {
+                if self.peek(0)?.is_falsey() {
+                    self.ip += offset;
+                }
+            }
+            OpCode::OpJumpIfNotNil(offset) => {
+                if !matches!(self.peek(0)?, Value::Nil) {
+                    self.ip += offset;
+                }
+            }
+            OpCode::OpJumpIfEqual(offset) => {
+                // Pops the case value but leaves the match subject in place.
+                let value = self.pop()?;
+                if self.peek(0)?.equals(&value) {
+                    self.ip += offset;
+                }
+            }
+            OpCode::OpBuildList(count) => {
+                let items = self.pop_n(count)?;
+                self.push(Value::list(items));
+            }
+            OpCode::OpToString => {
+                let value = self.pop()?;
+                let value = if value.is_string() {
+                    value
+                } else {
+                    Value::string(value.to_string())
+                };
+                self.push(value);
+            }
+            OpCode::OpRange(inclusive) => {
+                let end = self.pop()?;
+                let start = self.pop()?;
+                let (Value::Int(start), Value::Int(end)) = (start, end) else {
+                    return Err(self.runtime_error("Range bounds must be integers."));
+                };
+                self.push(Value::range(Range {
+                    start,
+                    end,
+                    inclusive,
+                }));
+            }
+            OpCode::OpBuildMap(count) => {
+                let entries = self.pop_n(count * 2)?;
+                let mut table = Table::new();
+                for entry in entries.chunks(2) {
+                    table.set(self.table_key(&entry[0])?, entry[1].clone());
+                }
+                self.push(Value::map(table));
+            }
+            OpCode::OpGetIndex => {
+                let index = self.pop()?;
+                let receiver = self.pop()?;
+                let value = self.get_index(&receiver, &index)?;
+                self.push(value);
+            }
+            OpCode::OpSetIndex => {
+                let value = self.pop()?;
+                let index = self.pop()?;
+                let receiver = self.pop()?;
+                self.set_index(&receiver, &index, value.clone())?;
+                self.push(value);
+            }
+            OpCode::OpInvoke(name, arg_count) => {
+                let args = self.pop_n(arg_count)?;
+                let receiver = self.pop()?;
+                let name = self.read_constant(name).to_string();
+                let result = self.invoke(&receiver, &name, args)?;
+                self.push(result);
+            }
         }
         Ok(InterpreterStatus::Running)
     }
     fn read_byte(&mut self) -> Result<OpCode, InterpreterError> {
-        self.ip += 1;
-        if self.ip > self.chunk.code.len() {
-            return Err(InterpreterError::CompileError(
-                self.chunk.linenum[self.ip - 1],
-            ));
+        if self.ip >= self.chunk.code.len() {
+            return Err(InterpreterError::CompileError(self.current_line()));
         }
+        self.ip += 1;
         Ok(self.chunk.code[self.ip - 1])
     }
-    fn read_constant(&self, index: usize) -> f64 {
+    fn read_constant(&self, index: usize) -> Value {
         self.chunk.read_constant(index)
     }
+    fn get_index(&self, receiver: &Value, index: &Value) -> Result<Value, InterpreterError> {
+        if let Some(range) = index.as_range() {
+            return match receiver.as_obj() {
+                Some(Obj::List(items)) => {
+                    let items = items.borrow();
+                    let (start, end) = self.slice_bounds(range, items.len())?;
+                    Ok(Value::list(items[start..end].to_vec()))
+                }
+                // Strings are sliced by character, not by byte.
+                Some(Obj::String(s)) => {
+                    let (start, end) = self.slice_bounds(range, s.chars().count())?;
+                    let slice = s.chars().skip(start).take(end - start).collect();
+                    Ok(Value::string(slice))
+                }
+                _ => Err(self.runtime_error("Only lists and strings can be sliced.")),
+            };
+        }
+        if let Value::Obj(obj) = receiver {
+            match obj.as_ref() {
+                Obj::List(items) => {
+                    let items = items.borrow();
+                    return Ok(items[self.list_index(index, items.len())?].clone());
+                }
+                // Missing keys read as nil so that `map[key] ?? default` works.
+                Obj::Map(table) => {
+                    let key = self.table_key(index)?;
+                    return Ok(table.borrow().get(&key).cloned().unwrap_or(Value::Nil));
+                }
+                _ => {}
+            }
+        }
+        Err(self.runtime_error("Only lists and maps can be indexed."))
+    }
+    fn set_index(
+        &self,
+        receiver: &Value,
+        index: &Value,
+        value: Value,
+    ) -> Result<(), InterpreterError> {
+        if let Value::Obj(obj) = receiver {
+            match obj.as_ref() {
+                Obj::List(items) => {
+                    let mut items = items.borrow_mut();
+                    let index = self.list_index(index, items.len())?;
+                    items[index] = value;
+                    return Ok(());
+                }
+                Obj::Map(table) => {
+                    table.borrow_mut().set(self.table_key(index)?, value);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Err(self.runtime_error("Only lists and maps can be indexed."))
+    }
+    fn invoke(
+        &self,
+        receiver: &Value,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        if let Value::Obj(obj) = receiver {
+            match obj.as_ref() {
+                Obj::List(items) => return self.invoke_list(items, name, args),
+                Obj::Map(table) => return self.invoke_map(table, name, args),
+                _ => {}
+            }
+        }
+        Err(self.runtime_error(&format!(
+            "Undefined method '{}' on {}.",
+            name,
+            receiver.type_name()
+        )))
+    }
+    fn invoke_list(
+        &self,
+        items: &RefCell<Vec<Value>>,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        match name {
+            "push" => {
+                let [value] = self.take_args(args)?;
+                items.borrow_mut().push(value);
+                Ok(Value::Nil)
+            }
+            "pop" => {
+                let [] = self.take_args(args)?;
+                let value = items.borrow_mut().pop();
+                value.ok_or_else(|| self.runtime_error("Cannot pop from an empty list."))
+            }
+            "len" => {
+                let [] = self.take_args(args)?;
+                Ok(Value::Int(items.borrow().len() as i64))
+            }
+            "insert" => {
+                // Insertion may also target one past the end, so -1 appends.
+                let [index, value] = self.take_args(args)?;
+                let mut items = items.borrow_mut();
+                let index = self.list_index(&index, items.len() + 1)?;
+                items.insert(index, value);
+                Ok(Value::Nil)
+            }
+            "remove" => {
+                let [index] = self.take_args(args)?;
+                let mut items = items.borrow_mut();
+                let index = self.list_index(&index, items.len())?;
+                Ok(items.remove(index))
+            }
+            _ => Err(self.runtime_error(&format!("Undefined method '{}' on list.", name))),
+        }
+    }
+    fn invoke_map(
+        &self,
+        table: &RefCell<Table>,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        match name {
+            "keys" => {
+                let [] = self.take_args(args)?;
+                let keys = table
+                    .borrow()
+                    .iter()
+                    .map(|(key, _)| key.to_value())
+                    .collect();
+                Ok(Value::list(keys))
+            }
+            "values" => {
+                let [] = self.take_args(args)?;
+                let values = table
+                    .borrow()
+                    .iter()
+                    .map(|(_, value)| value.clone())
+                    .collect();
+                Ok(Value::list(values))
+            }
+            "has" => {
+                let [key] = self.take_args(args)?;
+                let key = self.table_key(&key)?;
+                Ok(Value::Bool(table.borrow().get(&key).is_some()))
+            }
+            "remove" => {
+                let [key] = self.take_args(args)?;
+                let key = self.table_key(&key)?;
+                Ok(table.borrow_mut().remove(&key).unwrap_or(Value::Nil))
+            }
+            "len" => {
+                let [] = self.take_args(args)?;
+                Ok(Value::Int(table.borrow().len() as i64))
+            }
+            _ => Err(self.runtime_error(&format!("Undefined method '{}' on map.", name))),
+        }
+    }
+    fn table_key(&self, key: &Value) -> Result<TableKey, InterpreterError> {
+        TableKey::new(key)
+            .ok_or_else(|| self.runtime_error("Map key must be a string, number, boolean or nil."))
+    }
+    fn take_args<const N: usize>(&self, args: Vec<Value>) -> Result<[Value; N], InterpreterError> {
+        let count = args.len();
+        args.try_into().map_err(|_| {
+            self.runtime_error(&format!("Expected {} arguments but got {}.", N, count))
+        })
+    }
+    /// Negative indices count back from the end of the list.
+    fn list_index(&self, index: &Value, len: usize) -> Result<usize, InterpreterError> {
+        let Value::Int(index) = index else {
+            return Err(self.runtime_error("List index must be an integer."));
+        };
+        let index = if *index < 0 {
+            *index + len as i64
+        } else {
+            *index
+        };
+        if index < 0 || index >= len as i64 {
+            return Err(self.runtime_error("List index out of range."));
+        }
+        Ok(index as usize)
+    }
+    /// Resolves a slice to `start..end` within `0..=len`. Negative bounds
+    /// count back from the end, as with list indices.
+    fn slice_bounds(&self, range: &Range, len: usize) -> Result<(usize, usize), InterpreterError> {
+        let len = len as i64;
+        let resolve = |bound: i64| if bound < 0 { bound + len } else { bound };
+        let start = resolve(range.start);
+        let mut end = resolve(range.end);
+        if range.inclusive {
+            end = end.saturating_add(1);
+        }
+        if start < 0 || start > end || end > len {
+            return Err(self.runtime_error("Slice out of range."));
+        }
+        Ok((start as usize, end as usize))
+    }
+    /// Line of the instruction being executed, or 0 before the first one.
+    fn current_line(&self) -> usize {
+        self.ip
+            .checked_sub(1)
+            .and_then(|ip| self.chunk.linenum.get(ip))
+            .copied()
+            .unwrap_or(0)
+    }
+    fn runtime_error(&self, message: &str) -> InterpreterError {
+        let line = self.current_line();
+        println!("Runtime error at line {} : {}", line, message);
+        InterpreterError::RuntimeError(line)
+    }
     pub fn push(&mut self, value: Value) -> usize {
         self.stack.push(value);
         self.stack_top += 1;
         self.stack_top
     }
+    pub fn peek(&self, distance: usize) -> Result<Value, InterpreterError> {
+        self.stack
+            .len()
+            .checked_sub(distance + 1)
+            .map(|index| self.stack[index].clone())
+            .ok_or(InterpreterError::RuntimeError(self.current_line()))
+    }
+    fn pop_n(&mut self, count: usize) -> Result<Vec<Value>, InterpreterError> {
+        let start = self
+            .stack
+            .len()
+            .checked_sub(count)
+            .ok_or(InterpreterError::RuntimeError(self.current_line()))?;
+        self.stack_top -= count;
+        Ok(self.stack.split_off(start))
+    }
     pub fn pop(&mut self) -> Result<Value, InterpreterError> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or(InterpreterError::RuntimeError(self.current_line()))?;
         self.stack_top -= 1;
-        self.stack.pop().ok_or(InterpreterError::RuntimeError(
-            self.chunk.linenum[self.ip - 1],
-        ))
+        Ok(value)
     }
 }
 impl Default for VM {
@@ -124,3 +552,54 @@ impl Default for VM {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: i64, end: i64, inclusive: bool) -> Value {
+        Value::range(Range {
+            start,
+            end,
+            inclusive,
+        })
+    }
+
+    #[test]
+    fn inclusive_slice_to_last_element() {
+        let vm = VM::new();
+        let list = Value::list(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+        let slice = vm.get_index(&list, &range(0, -1, true)).unwrap();
+        assert!(matches!(slice.as_obj(), Some(Obj::List(items)) if items.borrow().len() == 3));
+        let empty = Value::list(Vec::new());
+        let slice = vm.get_index(&empty, &range(0, -1, true)).unwrap();
+        assert!(matches!(slice.as_obj(), Some(Obj::List(items)) if items.borrow().is_empty()));
+    }
+
+    #[test]
+    fn string_slices_by_character() {
+        let vm = VM::new();
+        let s = Value::string("héllo".to_string());
+        let slice = vm.get_index(&s, &range(1, 3, false)).unwrap();
+        assert!(slice.equals(&Value::string("él".to_string())));
+        let slice = vm.get_index(&s, &range(-2, -1, true)).unwrap();
+        assert!(slice.equals(&Value::string("lo".to_string())));
+    }
+
+    #[test]
+    fn slice_bounds_out_of_range() {
+        let bounds = |start, end, inclusive, len| {
+            let range = Range {
+                start,
+                end,
+                inclusive,
+            };
+            VM::new().slice_bounds(&range, len).ok()
+        };
+        assert_eq!(bounds(0, 5, false, 5), Some((0, 5)));
+        assert_eq!(bounds(0, 5, true, 5), None);
+        assert_eq!(bounds(2, 1, false, 5), None);
+        assert_eq!(bounds(-6, 0, false, 5), None);
+        assert_eq!(bounds(0, i64::MAX, true, 5), None);
+    }
+}